@@ -1,7 +1,12 @@
+use png::{BitDepth, ColorType, Encoder};
 use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsCast;
 use web_sys::{CanvasRenderingContext2d, HtmlCanvasElement, ImageData};
 
+mod palette;
+mod perturbation;
+mod view_state;
+
 // Console logging macro
 macro_rules! log {
     ( $( $t:tt )* ) => {
@@ -9,6 +14,36 @@ macro_rules! log {
     }
 }
 
+// Which escape-time formula to iterate per pixel.
+#[derive(Clone, Copy, PartialEq)]
+enum FractalKind {
+    Mandelbrot,
+    BurningShip,
+    Tricorn,
+    Multibrot,
+}
+
+impl FractalKind {
+    fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "mandelbrot" => Some(FractalKind::Mandelbrot),
+            "burning_ship" => Some(FractalKind::BurningShip),
+            "tricorn" => Some(FractalKind::Tricorn),
+            "multibrot" => Some(FractalKind::Multibrot),
+            _ => None,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            FractalKind::Mandelbrot => "mandelbrot",
+            FractalKind::BurningShip => "burning_ship",
+            FractalKind::Tricorn => "tricorn",
+            FractalKind::Multibrot => "multibrot",
+        }
+    }
+}
+
 // Fractal viewport state
 #[derive(Clone, Copy)]
 struct Viewport {
@@ -18,6 +53,11 @@ struct Viewport {
     width: u32,
     height: u32,
     max_iter: u32,
+    fractal: FractalKind,
+    power: u32,           // exponent used by Multibrot
+    julia_seed: Option<(f64, f64)>, // Some(c) switches to Julia mode with this fixed seed
+    palette: &'static str,
+    palette_cycle: f64,   // [0, 1) offset rotated into the palette lookup
 }
 
 thread_local! {
@@ -28,8 +68,57 @@ thread_local! {
         width: 800,
         height: 600,
         max_iter: 256,
+        fractal: FractalKind::Mandelbrot,
+        power: 2,
+        julia_seed: None,
+        palette: "classic",
+        palette_cycle: 0.0,
     });
     static CTX: std::cell::RefCell<Option<CanvasRenderingContext2d>> = std::cell::RefCell::new(None);
+    static PALETTE_LUT: std::cell::RefCell<palette::Lut> =
+        std::cell::RefCell::new(palette::build_lut(palette::CLASSIC));
+    static LAST_MU: std::cell::RefCell<Vec<f64>> = std::cell::RefCell::new(Vec::new());
+    // One entry per row of `LAST_MU`, tracking which rows a progressive
+    // `render_tile()` sequence has actually painted so `recolor()` can tell a
+    // fully-covered frame from one still mid-tile.
+    static ROWS_COVERED: std::cell::RefCell<Vec<bool>> = std::cell::RefCell::new(Vec::new());
+    static ANIMATION: std::cell::RefCell<Option<AnimationKeyframes>> = std::cell::RefCell::new(None);
+    // Keyed on (center_x, center_y, max_iter): the reference orbit only
+    // depends on these, so a progressive render split across many
+    // `render_tile()` calls reuses one orbit instead of recomputing the
+    // expensive double-double iteration per tile.
+    static REFERENCE_ORBIT_CACHE: std::cell::RefCell<Option<(f64, f64, u32, std::rc::Rc<Vec<(f64, f64)>>)>> =
+        std::cell::RefCell::new(None);
+}
+
+fn reference_orbit_for(center_x: f64, center_y: f64, max_iter: u32) -> std::rc::Rc<Vec<(f64, f64)>> {
+    REFERENCE_ORBIT_CACHE.with(|cache| {
+        let mut cache = cache.borrow_mut();
+        if let Some((cx, cy, mi, orbit)) = cache.as_ref() {
+            if *cx == center_x && *cy == center_y && *mi == max_iter {
+                return orbit.clone();
+            }
+        }
+
+        let orbit = std::rc::Rc::new(perturbation::reference_orbit(center_x, center_y, max_iter));
+        *cache = Some((center_x, center_y, max_iter, orbit.clone()));
+        orbit
+    })
+}
+
+// A zoom animation interpolating from the viewport active when `animate()`
+// was called to a target keyframe, over a fixed frame count.
+#[derive(Clone, Copy)]
+struct AnimationKeyframes {
+    start_center_x: f64,
+    start_center_y: f64,
+    start_range: f64,
+    start_max_iter: u32,
+    target_x: f64,
+    target_y: f64,
+    target_range: f64,
+    frames: u32,
+    next_frame: u32,
 }
 
 #[wasm_bindgen(start)]
@@ -69,18 +158,77 @@ pub fn init(canvas_id: &str) -> Result<(), JsValue> {
 pub fn render() -> Result<(), JsValue> {
     let (viewport, ctx) = get_state_and_ctx()?;
 
+    let (data, mu_buf) = compute_frame(&viewport);
+    LAST_MU.with(|m| *m.borrow_mut() = mu_buf);
+    ROWS_COVERED.with(|r| *r.borrow_mut() = vec![true; viewport.height as usize]);
+
+    let clamped = wasm_bindgen::Clamped(&data[..]);
+    let image_data = ImageData::new_with_u8_clamped_array_and_sh(
+        clamped,
+        viewport.width,
+        viewport.height,
+    )?;
+
+    ctx.put_image_data(&image_data, 0.0, 0.0)?;
+    Ok(())
+}
+
+// Renders an RGBA buffer for the given viewport (which may have different
+// dimensions than the on-screen canvas, e.g. for high-res PNG export) and
+// the matching per-pixel smooth iteration counts.
+fn compute_frame(viewport: &Viewport) -> (Vec<u8>, Vec<f64>) {
+    compute_rows(viewport, 0, viewport.height as usize)
+}
+
+// Renders just rows `[y_start, y_end)` of `viewport`, as a resumable work
+// unit for progressive/tiled rendering (see `render_tile`). The returned
+// buffers cover only those rows, not the full frame.
+fn compute_rows(viewport: &Viewport, y_start: usize, y_end: usize) -> (Vec<u8>, Vec<f64>) {
     let width = viewport.width as usize;
-    let height = viewport.height as usize;
+    let rows = y_end - y_start;
 
-    let mut data = vec![0u8; width * height * 4];
+    let mut data = vec![0u8; width * rows * 4];
+    let mut mu_buf = vec![0.0f64; width * rows];
 
-    for py in 0..height {
-        for px in 0..width {
-            let (re, im) = map_pixel_to_complex(px as f64, py as f64, &viewport);
-            let iter = calculate_mandelbrot(re, im, viewport.max_iter);
-            let (r, g, b) = get_color(iter, viewport.max_iter);
+    // Perturbation only applies to the standard Mandelbrot map, since its
+    // delta recurrence is derived from z^2 + c; other kinds and Julia mode
+    // (which varies z0 rather than c) keep using direct iteration.
+    let use_perturbation = viewport.julia_seed.is_none()
+        && viewport.fractal == FractalKind::Mandelbrot
+        && viewport.range < PERTURBATION_RANGE_THRESHOLD;
+
+    let reference_orbit = use_perturbation
+        .then(|| reference_orbit_for(viewport.center_x, viewport.center_y, viewport.max_iter));
+
+    let lut = PALETTE_LUT.with(|l| *l.borrow());
 
-            let idx = (py * width + px) * 4;
+    for py in y_start..y_end {
+        for px in 0..width {
+            let mu = if let Some(orbit) = &reference_orbit {
+                // Computed straight from the pixel index and range, never by
+                // forming the absolute complex coordinate and subtracting
+                // `center_x`/`center_y` back out: once the per-pixel step is
+                // smaller than the center's ulp, that round-trip would
+                // silently swallow the offset and defeat perturbation.
+                let (delta_c_re, delta_c_im) = map_pixel_to_delta(px as f64, py as f64, viewport);
+                perturbation::calculate_perturbed(delta_c_re, delta_c_im, orbit, viewport.max_iter)
+            } else {
+                let (px_re, px_im) = map_pixel_to_complex(px as f64, py as f64, viewport);
+                let (z0_re, z0_im, c_re, c_im) = match viewport.julia_seed {
+                    Some((seed_re, seed_im)) => (px_re, px_im, seed_re, seed_im),
+                    None => (0.0, 0.0, px_re, px_im),
+                };
+                calculate_fractal(
+                    z0_re, z0_im, c_re, c_im, viewport.max_iter, viewport.fractal, viewport.power,
+                )
+            };
+
+            let pixel = (py - y_start) * width + px;
+            mu_buf[pixel] = mu;
+
+            let (r, g, b) = get_color(mu, viewport.max_iter, &lut, viewport.palette_cycle);
+
+            let idx = pixel * 4;
             data[idx] = r;
             data[idx + 1] = g;
             data[idx + 2] = b;
@@ -88,6 +236,124 @@ pub fn render() -> Result<(), JsValue> {
         }
     }
 
+    (data, mu_buf)
+}
+
+// Encodes the current on-screen render to PNG bytes, independent of canvas size.
+#[wasm_bindgen]
+pub fn export_png() -> Result<Vec<u8>, JsValue> {
+    let viewport = VIEWPORT.with(|v| *v.borrow());
+    let (data, _) = compute_frame(&viewport);
+    encode_png(&data, viewport.width, viewport.height)
+}
+
+// Renders a still at `width`x`height`, optionally at `supersample`x that
+// resolution box-downsampled back down for anti-aliasing, and encodes it to
+// PNG. Lets users export a higher-res image than the on-screen canvas.
+#[wasm_bindgen]
+pub fn export_png_hires(width: u32, height: u32, supersample: u32) -> Result<Vec<u8>, JsValue> {
+    let supersample = supersample.max(1);
+
+    let render_width = width
+        .checked_mul(supersample)
+        .ok_or_else(|| JsValue::from_str("requested resolution is too large"))?;
+    let render_height = height
+        .checked_mul(supersample)
+        .ok_or_else(|| JsValue::from_str("requested resolution is too large"))?;
+
+    let mut viewport = VIEWPORT.with(|v| *v.borrow());
+    viewport.width = render_width;
+    viewport.height = render_height;
+
+    let (data, _) = compute_frame(&viewport);
+    let data = downsample(&data, viewport.width, viewport.height, supersample);
+
+    encode_png(&data, width, height)
+}
+
+// Averages each `factor`x`factor` block of an RGBA buffer down to one pixel.
+fn downsample(data: &[u8], src_width: u32, src_height: u32, factor: u32) -> Vec<u8> {
+    if factor == 1 {
+        return data.to_vec();
+    }
+
+    let dst_width = src_width / factor;
+    let dst_height = src_height / factor;
+    let mut out = vec![0u8; (dst_width * dst_height * 4) as usize];
+    let samples = (factor * factor) as u32;
+
+    for dy in 0..dst_height {
+        for dx in 0..dst_width {
+            let mut sums = [0u32; 4];
+            for sy in 0..factor {
+                for sx in 0..factor {
+                    let src_x = dx * factor + sx;
+                    let src_y = dy * factor + sy;
+                    let src_idx = ((src_y * src_width + src_x) * 4) as usize;
+                    for (channel, sum) in sums.iter_mut().enumerate() {
+                        *sum += data[src_idx + channel] as u32;
+                    }
+                }
+            }
+
+            let dst_idx = ((dy * dst_width + dx) * 4) as usize;
+            for (channel, sum) in sums.iter().enumerate() {
+                out[dst_idx + channel] = (sum / samples) as u8;
+            }
+        }
+    }
+
+    out
+}
+
+fn encode_png(rgba: &[u8], width: u32, height: u32) -> Result<Vec<u8>, JsValue> {
+    let mut bytes = Vec::new();
+    {
+        let mut encoder = Encoder::new(&mut bytes, width, height);
+        encoder.set_color(ColorType::Rgba);
+        encoder.set_depth(BitDepth::Eight);
+
+        let mut writer = encoder
+            .write_header()
+            .map_err(|e| JsValue::from_str(&format!("png header error: {e}")))?;
+        writer
+            .write_image_data(rgba)
+            .map_err(|e| JsValue::from_str(&format!("png encode error: {e}")))?;
+    }
+    Ok(bytes)
+}
+
+// Re-colors the last rendered frame from the cached escape values without
+// re-iterating the fractal; used after a palette/cycle change for a snappy
+// re-color-only update. Falls back to a full `render()` if nothing has been
+// rendered yet, the canvas size has since changed, or a progressive
+// `render_tile()` sequence hasn't finished covering every row yet.
+fn recolor() -> Result<(), JsValue> {
+    let (viewport, ctx) = get_state_and_ctx()?;
+    let width = viewport.width as usize;
+    let height = viewport.height as usize;
+
+    let mu_buf = LAST_MU.with(|m| m.borrow().clone());
+    let fully_covered = ROWS_COVERED.with(|r| {
+        let covered = r.borrow();
+        covered.len() == height && covered.iter().all(|&done| done)
+    });
+    if mu_buf.len() != width * height || !fully_covered {
+        return render();
+    }
+
+    let lut = PALETTE_LUT.with(|l| *l.borrow());
+    let mut data = vec![0u8; width * height * 4];
+
+    for (pixel, &mu) in mu_buf.iter().enumerate() {
+        let (r, g, b) = get_color(mu, viewport.max_iter, &lut, viewport.palette_cycle);
+        let idx = pixel * 4;
+        data[idx] = r;
+        data[idx + 1] = g;
+        data[idx + 2] = b;
+        data[idx + 3] = 255;
+    }
+
     let clamped = wasm_bindgen::Clamped(&data[..]);
     let image_data = ImageData::new_with_u8_clamped_array_and_sh(
         clamped,
@@ -99,6 +365,181 @@ pub fn render() -> Result<(), JsValue> {
     Ok(())
 }
 
+#[wasm_bindgen]
+pub fn set_palette(name: &str) -> Result<(), JsValue> {
+    let (canonical, stops) =
+        palette::resolve(name).ok_or_else(|| JsValue::from_str("unknown palette"))?;
+
+    VIEWPORT.with(|v| v.borrow_mut().palette = canonical);
+    PALETTE_LUT.with(|l| *l.borrow_mut() = palette::build_lut(stops));
+
+    recolor()
+}
+
+#[wasm_bindgen]
+pub fn set_palette_cycle(offset: f64) -> Result<(), JsValue> {
+    VIEWPORT.with(|v| v.borrow_mut().palette_cycle = offset.rem_euclid(1.0));
+    recolor()
+}
+
+// Exports the full view (center, range, max_iter, fractal kind, Julia seed,
+// palette) as a compact JSON string, suitable for bookmarking or sharing.
+#[wasm_bindgen]
+pub fn get_state() -> Result<String, JsValue> {
+    let viewport = VIEWPORT.with(|v| *v.borrow());
+
+    let state = view_state::ViewState {
+        center_x: viewport.center_x,
+        center_y: viewport.center_y,
+        range: viewport.range,
+        max_iter: viewport.max_iter,
+        fractal: viewport.fractal.as_str().to_string(),
+        power: viewport.power,
+        julia_seed: viewport.julia_seed,
+        palette: viewport.palette.to_string(),
+        palette_cycle: viewport.palette_cycle,
+    };
+
+    serde_json::to_string(&state).map_err(|e| JsValue::from_str(&format!("serialize error: {e}")))
+}
+
+// Restores a view previously produced by `get_state()`. Fields are clamped
+// to sane ranges so a hand-edited or corrupted string can't leave the
+// viewport in a broken state.
+#[wasm_bindgen]
+pub fn set_state(s: &str) -> Result<(), JsValue> {
+    let state: view_state::ViewState =
+        serde_json::from_str(s).map_err(|e| JsValue::from_str(&format!("parse error: {e}")))?;
+    let state = state.clamp();
+
+    let fractal = FractalKind::from_str(&state.fractal)
+        .ok_or_else(|| JsValue::from_str("unknown fractal kind"))?;
+    let (palette_name, stops) =
+        palette::resolve(&state.palette).ok_or_else(|| JsValue::from_str("unknown palette"))?;
+
+    VIEWPORT.with(|v| {
+        let mut vp = v.borrow_mut();
+        vp.center_x = state.center_x;
+        vp.center_y = state.center_y;
+        vp.range = state.range;
+        vp.max_iter = state.max_iter;
+        vp.fractal = fractal;
+        vp.power = state.power;
+        vp.julia_seed = state.julia_seed;
+        vp.palette = palette_name;
+        vp.palette_cycle = state.palette_cycle;
+    });
+    PALETTE_LUT.with(|l| *l.borrow_mut() = palette::build_lut(stops));
+
+    render()
+}
+
+// Multibrot's per-pixel cost is O(power) on top of O(max_iter), so an
+// unbounded `power` (e.g. from a fat-fingered UI slider) can hang the
+// single-threaded WASM tab indefinitely. Shared by `set_fractal` and
+// `ViewState::clamp` so both entry points enforce the same ceiling.
+pub(crate) const MAX_FRACTAL_POWER: u32 = 16;
+
+#[wasm_bindgen]
+pub fn set_fractal(kind: &str, power: u32) -> Result<(), JsValue> {
+    let kind = FractalKind::from_str(kind)
+        .ok_or_else(|| JsValue::from_str("unknown fractal kind"))?;
+
+    VIEWPORT.with(|v| {
+        let mut vp = v.borrow_mut();
+        vp.fractal = kind;
+        vp.power = power.clamp(2, MAX_FRACTAL_POWER);
+    });
+
+    render()
+}
+
+#[wasm_bindgen]
+pub fn set_julia(seed_re: f64, seed_im: f64) -> Result<(), JsValue> {
+    VIEWPORT.with(|v| v.borrow_mut().julia_seed = Some((seed_re, seed_im)));
+    render()
+}
+
+#[wasm_bindgen]
+pub fn clear_julia() -> Result<(), JsValue> {
+    VIEWPORT.with(|v| v.borrow_mut().julia_seed = None);
+    render()
+}
+
+// Maps a canvas pixel to a Julia seed and re-renders with it, letting the
+// caller sweep the seed across the Mandelbrot plane to morph the Julia set.
+#[wasm_bindgen]
+pub fn julia_seed_at(px: f64, py: f64) -> Result<(), JsValue> {
+    let seed = VIEWPORT.with(|v| {
+        let vp = v.borrow();
+        map_pixel_to_complex(px, py, &vp)
+    });
+    VIEWPORT.with(|v| v.borrow_mut().julia_seed = Some(seed));
+    render()
+}
+
+// Starts a zoom animation from the current viewport to the given target
+// keyframe over `frames` frames. Returns the frame count; call
+// `next_animation_frame()` that many times to collect each rendered frame.
+#[wasm_bindgen]
+pub fn animate(target_x: f64, target_y: f64, target_range: f64, frames: u32) -> u32 {
+    let start = VIEWPORT.with(|v| *v.borrow());
+    let frames = frames.max(1);
+
+    ANIMATION.with(|a| {
+        *a.borrow_mut() = Some(AnimationKeyframes {
+            start_center_x: start.center_x,
+            start_center_y: start.center_y,
+            start_range: start.range,
+            start_max_iter: start.max_iter,
+            target_x,
+            target_y,
+            target_range,
+            frames,
+            next_frame: 0,
+        });
+    });
+
+    frames
+}
+
+// Renders and returns the next frame of the in-progress animation as a raw
+// RGBA buffer, advancing internal keyframe state. Returns an empty buffer
+// once all frames have been produced or no animation is in progress.
+// `range` is interpolated geometrically (so the zoom reads as constant
+// speed) while `center` interpolates linearly and `max_iter` ramps up as
+// `range` shrinks to keep deep frames detailed.
+#[wasm_bindgen]
+pub fn next_animation_frame() -> Vec<u8> {
+    let Some(mut keyframes) = ANIMATION.with(|a| *a.borrow()) else {
+        return Vec::new();
+    };
+    if keyframes.next_frame >= keyframes.frames {
+        return Vec::new();
+    }
+
+    let t = keyframes.next_frame as f64 / (keyframes.frames - 1).max(1) as f64;
+
+    let range = keyframes.start_range * (keyframes.target_range / keyframes.start_range).powf(t);
+    let center_x = keyframes.start_center_x + (keyframes.target_x - keyframes.start_center_x) * t;
+    let center_y = keyframes.start_center_y + (keyframes.target_y - keyframes.start_center_y) * t;
+    let max_iter =
+        (keyframes.start_max_iter as f64 * (keyframes.start_range / range).max(1.0).sqrt()) as u32;
+
+    let mut viewport = VIEWPORT.with(|v| *v.borrow());
+    viewport.center_x = center_x;
+    viewport.center_y = center_y;
+    viewport.range = range;
+    viewport.max_iter = max_iter;
+
+    let (data, _) = compute_frame(&viewport);
+
+    keyframes.next_frame += 1;
+    ANIMATION.with(|a| *a.borrow_mut() = Some(keyframes));
+
+    data
+}
+
 #[wasm_bindgen]
 pub fn zoom_at(x: f64, y: f64, zoom_factor: f64) -> Result<(), JsValue> {
     VIEWPORT.with(|v| {
@@ -112,6 +553,69 @@ pub fn zoom_at(x: f64, y: f64, zoom_factor: f64) -> Result<(), JsValue> {
     render()
 }
 
+// Shifts the viewport center by a pixel delta (e.g. from a click-drag),
+// converted through the current scale so the content under the cursor
+// follows the drag.
+#[wasm_bindgen]
+pub fn pan(dx_pixels: f64, dy_pixels: f64) -> Result<(), JsValue> {
+    VIEWPORT.with(|v| {
+        let mut vp = v.borrow_mut();
+        let aspect = vp.width as f64 / vp.height as f64;
+        let range_x = vp.range * aspect;
+
+        vp.center_x -= (dx_pixels / vp.width as f64) * range_x;
+        vp.center_y += (dy_pixels / vp.height as f64) * vp.range;
+    });
+
+    render()
+}
+
+// Renders and paints just rows `[y_start, y_end)` of the canvas, letting
+// callers split a full render into row tiles (e.g. a quick coarse pass
+// followed by finer tiles) instead of blocking on the whole frame at once.
+#[wasm_bindgen]
+pub fn render_tile(y_start: u32, y_end: u32) -> Result<(), JsValue> {
+    let (viewport, ctx) = get_state_and_ctx()?;
+    let y_end = y_end.min(viewport.height);
+    if y_start >= y_end {
+        return Ok(());
+    }
+
+    let (data, mu_rows) = compute_rows(&viewport, y_start as usize, y_end as usize);
+
+    // Merge this tile's escape values into the cached full-frame buffer, and
+    // mark its rows covered, so `recolor()` can tell once every row (not
+    // just a buffer of the right size) has actually landed.
+    LAST_MU.with(|m| {
+        let mut mu = m.borrow_mut();
+        let width = viewport.width as usize;
+        let expected = width * viewport.height as usize;
+        if mu.len() != expected {
+            *mu = vec![0.0; expected];
+        }
+        let start_idx = y_start as usize * width;
+        mu[start_idx..start_idx + mu_rows.len()].copy_from_slice(&mu_rows);
+    });
+
+    ROWS_COVERED.with(|r| {
+        let mut covered = r.borrow_mut();
+        let height = viewport.height as usize;
+        if covered.len() != height {
+            *covered = vec![false; height];
+        }
+        for row in covered.iter_mut().take(y_end as usize).skip(y_start as usize) {
+            *row = true;
+        }
+    });
+
+    let clamped = wasm_bindgen::Clamped(&data[..]);
+    let image_data =
+        ImageData::new_with_u8_clamped_array_and_sh(clamped, viewport.width, y_end - y_start)?;
+
+    ctx.put_image_data(&image_data, 0.0, y_start as f64)?;
+    Ok(())
+}
+
 fn get_state_and_ctx() -> Result<(Viewport, CanvasRenderingContext2d), JsValue> {
     let viewport = VIEWPORT.with(|v| *v.borrow());
     let ctx = CTX.with(|c| {
@@ -137,33 +641,105 @@ fn map_pixel_to_complex(px: f64, py: f64, vp: &Viewport) -> (f64, f64) {
     (x, y)
 }
 
-fn calculate_mandelbrot(re0: f64, im0: f64, max_iter: u32) -> u32 {
-    let mut re = 0.0;
-    let mut im = 0.0;
+// Same mapping as `map_pixel_to_complex`, but returns the pixel's offset
+// from the viewport center directly instead of the absolute coordinate.
+// Used by the perturbation path, where forming `center + offset` and then
+// subtracting `center` back out would round-trip through f64 and lose the
+// offset once it's smaller than the center's ulp.
+fn map_pixel_to_delta(px: f64, py: f64, vp: &Viewport) -> (f64, f64) {
+    let w = vp.width as f64;
+    let h = vp.height as f64;
+    let aspect = w / h;
+
+    let range_y = vp.range;
+    let range_x = vp.range * aspect;
+
+    let dx = (px / w - 0.5) * range_x;
+    let dy = (0.5 - py / h) * range_y;
+
+    (dx, dy)
+}
+
+// A bailout radius much larger than the classic 2.0 so |z| has settled into its
+// asymptotic growth by the time we escape, which is what the smooth/continuous
+// iteration count below assumes.
+pub(crate) const BAILOUT: f64 = 256.0;
+
+// Below this viewport range, plain f64 per-pixel iteration has lost too much
+// precision to be trustworthy and we switch to perturbation rendering.
+const PERTURBATION_RANGE_THRESHOLD: f64 = 1e-10;
+
+// `(z0_re, z0_im)` is the starting value being iterated and `(c_re, c_im)` is the
+// added constant. Mandelbrot-family modes start at zero and vary `c` per pixel;
+// Julia mode fixes `c` to a seed and varies the starting `z0` per pixel instead.
+//
+// Returns a fractional (smooth) iteration count mu = n + 1 - log_d(log(|z|)),
+// which removes the banding a raw integer escape count produces. The log
+// base `d` is the escape-rate exponent of the formula being iterated (2 for
+// every kind except Multibrot, which grows as z^power). Points that never
+// escape return `max_iter` exactly.
+fn calculate_fractal(
+    z0_re: f64,
+    z0_im: f64,
+    c_re: f64,
+    c_im: f64,
+    max_iter: u32,
+    kind: FractalKind,
+    power: u32,
+) -> f64 {
+    let growth_power = if kind == FractalKind::Multibrot { power as f64 } else { 2.0 };
+
+    let mut re = z0_re;
+    let mut im = z0_im;
     for n in 0..max_iter {
         let re2 = re * re;
         let im2 = im * im;
 
-        if re2 + im2 > 4.0 {
-            return n;
+        if re2 + im2 > BAILOUT * BAILOUT {
+            let modulus = (re2 + im2).sqrt();
+            return n as f64 + 1.0 - modulus.ln().log(growth_power);
         }
 
-        im = 2.0 * re * im + im0;
-        re = re2 - im2 + re0;
+        let (nre, nim) = match kind {
+            FractalKind::Mandelbrot => (re2 - im2 + c_re, 2.0 * re * im + c_im),
+            FractalKind::BurningShip => {
+                let are = re.abs();
+                let aim = im.abs();
+                (are * are - aim * aim + c_re, 2.0 * are * aim + c_im)
+            }
+            FractalKind::Tricorn => (re2 - im2 + c_re, -2.0 * re * im + c_im),
+            FractalKind::Multibrot => {
+                let (pre, pim) = complex_pow(re, im, power);
+                (pre + c_re, pim + c_im)
+            }
+        };
+        re = nre;
+        im = nim;
     }
-    max_iter
+    max_iter as f64
 }
 
-fn get_color(iter: u32, max_iter: u32) -> (u8, u8, u8) {
-    if iter == max_iter {
-        return (0, 0, 0);
+// Raises (re, im) to an integer power via repeated complex multiplication.
+fn complex_pow(re: f64, im: f64, power: u32) -> (f64, f64) {
+    let mut pre = 1.0;
+    let mut pim = 0.0;
+    for _ in 0..power {
+        let nre = pre * re - pim * im;
+        let nim = pre * im + pim * re;
+        pre = nre;
+        pim = nim;
     }
+    (pre, pim)
+}
 
-    let t = (iter as f64 / max_iter as f64).powf(0.5);
+fn get_color(iter: f64, max_iter: u32, lut: &palette::Lut, cycle: f64) -> (u8, u8, u8) {
+    if iter >= max_iter as f64 {
+        return (0, 0, 0);
+    }
 
-    let r = (9.0 * (1.0 - t) * t * t * t * 255.0) as u8;
-    let g = (15.0 * (1.0 - t) * (1.0 - t) * t * t * 255.0) as u8;
-    let b = (8.5 * (1.0 - t) * (1.0 - t) * (1.0 - t) * t * 255.0) as u8;
+    let t = (iter / max_iter as f64).clamp(0.0, 1.0).powf(0.5);
+    let cycled = (t + cycle).rem_euclid(1.0);
+    let idx = (cycled * (palette::LUT_SIZE - 1) as f64).round() as usize;
 
-    (r, g, b)
+    lut[idx.min(palette::LUT_SIZE - 1)]
 }
@@ -0,0 +1,73 @@
+// Named colormap gradients, each a set of RGB control-point stops that are
+// linearly interpolated into a fixed-size lookup table. Selecting a palette
+// (or rotating it) only rebuilds/re-indexes this table; it never touches the
+// per-pixel fractal iteration.
+
+pub(crate) const LUT_SIZE: usize = 256;
+
+pub(crate) type Stop = (f64, (u8, u8, u8));
+pub(crate) type Lut = [(u8, u8, u8); LUT_SIZE];
+
+pub(crate) const CLASSIC: &[Stop] = &[
+    (0.0, (0, 7, 100)),
+    (0.16, (32, 107, 203)),
+    (0.42, (237, 255, 255)),
+    (0.6425, (255, 170, 0)),
+    (0.8575, (0, 2, 0)),
+    (1.0, (0, 7, 100)),
+];
+
+const GRAYSCALE: &[Stop] = &[(0.0, (0, 0, 0)), (1.0, (255, 255, 255))];
+
+const FIRE: &[Stop] = &[
+    (0.0, (0, 0, 0)),
+    (0.35, (128, 0, 0)),
+    (0.6, (255, 120, 0)),
+    (0.85, (255, 230, 80)),
+    (1.0, (255, 255, 255)),
+];
+
+const VIRIDIS: &[Stop] = &[
+    (0.0, (68, 1, 84)),
+    (0.25, (59, 82, 139)),
+    (0.5, (33, 145, 140)),
+    (0.75, (94, 201, 98)),
+    (1.0, (253, 231, 37)),
+];
+
+// Resolves a palette name to its canonical `'static` name (for storing on
+// `Viewport`) and its stop list.
+pub(crate) fn resolve(name: &str) -> Option<(&'static str, &'static [Stop])> {
+    match name {
+        "classic" => Some(("classic", CLASSIC)),
+        "grayscale" => Some(("grayscale", GRAYSCALE)),
+        "fire" => Some(("fire", FIRE)),
+        "viridis" => Some(("viridis", VIRIDIS)),
+        _ => None,
+    }
+}
+
+pub(crate) fn build_lut(stops: &[Stop]) -> Lut {
+    let mut lut = [(0u8, 0u8, 0u8); LUT_SIZE];
+    for (i, entry) in lut.iter_mut().enumerate() {
+        let t = i as f64 / (LUT_SIZE - 1) as f64;
+        *entry = interpolate(stops, t);
+    }
+    lut
+}
+
+fn interpolate(stops: &[Stop], t: f64) -> (u8, u8, u8) {
+    for pair in stops.windows(2) {
+        let (t0, c0) = pair[0];
+        let (t1, c1) = pair[1];
+        if t >= t0 && t <= t1 {
+            let local = if t1 > t0 { (t - t0) / (t1 - t0) } else { 0.0 };
+            return (lerp(c0.0, c1.0, local), lerp(c0.1, c1.1, local), lerp(c0.2, c1.2, local));
+        }
+    }
+    stops.last().expect("palette must have at least one stop").1
+}
+
+fn lerp(a: u8, b: u8, t: f64) -> u8 {
+    (a as f64 + (b as f64 - a as f64) * t).round() as u8
+}
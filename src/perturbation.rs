@@ -0,0 +1,210 @@
+// Perturbation-based deep zoom for the Mandelbrot set.
+//
+// Plain f64 iteration loses all precision once the viewport range shrinks
+// past roughly 1e-13 (the pixel-to-pixel delta in `c` falls below f64
+// epsilon). Perturbation theory sidesteps this: iterate one full-precision
+// "reference" orbit at the viewport center once, then for every pixel track
+// only the *difference* from that orbit (`delta`), which stays small and
+// representable in plain f64 even when the absolute coordinates are not.
+//
+// The reference orbit itself is computed in double-double precision (~106
+// bits) so it doesn't immediately reintroduce the precision wall it's meant
+// to avoid.
+
+use crate::BAILOUT;
+
+// A double-double float: `hi + lo` represents a value with roughly twice
+// the precision of a single f64. Implemented manually (Dekker/Knuth
+// algorithms) since this is the only place that needs extra precision and
+// doesn't warrant a big-float dependency.
+#[derive(Clone, Copy)]
+struct DoubleDouble {
+    hi: f64,
+    lo: f64,
+}
+
+impl DoubleDouble {
+    fn from_f64(x: f64) -> Self {
+        DoubleDouble { hi: x, lo: 0.0 }
+    }
+
+    fn to_f64(self) -> f64 {
+        self.hi + self.lo
+    }
+
+    fn two_sum(a: f64, b: f64) -> (f64, f64) {
+        let s = a + b;
+        let bb = s - a;
+        let err = (a - (s - bb)) + (b - bb);
+        (s, err)
+    }
+
+    fn two_prod(a: f64, b: f64) -> (f64, f64) {
+        let p = a * b;
+        let err = a.mul_add(b, -p);
+        (p, err)
+    }
+
+    fn add(self, other: Self) -> Self {
+        let (s, e) = Self::two_sum(self.hi, other.hi);
+        let (hi, lo) = Self::two_sum(s, e + self.lo + other.lo);
+        DoubleDouble { hi, lo }
+    }
+
+    fn sub(self, other: Self) -> Self {
+        self.add(DoubleDouble { hi: -other.hi, lo: -other.lo })
+    }
+
+    fn mul(self, other: Self) -> Self {
+        let (p, e) = Self::two_prod(self.hi, other.hi);
+        let (hi, lo) = Self::two_sum(p, e + self.hi * other.lo + self.lo * other.hi);
+        DoubleDouble { hi, lo }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct ComplexDD {
+    re: DoubleDouble,
+    im: DoubleDouble,
+}
+
+impl ComplexDD {
+    fn new(re: f64, im: f64) -> Self {
+        ComplexDD {
+            re: DoubleDouble::from_f64(re),
+            im: DoubleDouble::from_f64(im),
+        }
+    }
+
+    fn add(self, other: Self) -> Self {
+        ComplexDD {
+            re: self.re.add(other.re),
+            im: self.im.add(other.im),
+        }
+    }
+
+    fn square(self) -> Self {
+        let re = self.re.mul(self.re).sub(self.im.mul(self.im));
+        let im = self.re.mul(self.im).add(self.re.mul(self.im));
+        ComplexDD { re, im }
+    }
+}
+
+// Computes the full-precision orbit `Z_0, Z_1, ...` of the reference point
+// `center` under `z = z^2 + c`, stopping early if it escapes. Only the
+// standard quadratic Mandelbrot map is supported; Julia mode and the other
+// fractal kinds fall back to direct per-pixel iteration.
+pub(crate) fn reference_orbit(center_re: f64, center_im: f64, max_iter: u32) -> Vec<(f64, f64)> {
+    let c = ComplexDD::new(center_re, center_im);
+    let mut z = ComplexDD::new(0.0, 0.0);
+    let mut orbit = Vec::with_capacity(max_iter as usize + 1);
+    orbit.push((0.0, 0.0));
+
+    for _ in 0..max_iter {
+        z = z.square().add(c);
+        let re = z.re.to_f64();
+        let im = z.im.to_f64();
+        orbit.push((re, im));
+        if re * re + im * im > BAILOUT * BAILOUT {
+            break;
+        }
+    }
+
+    orbit
+}
+
+// Iterates the low-precision delta recurrence
+// `delta_{n+1} = 2*Z_n*delta_n + delta_n^2 + delta_c` against a precomputed
+// reference orbit, where the true point is `z_n = Z_n + delta_n`. Applies
+// Pauldelbrot rebasing: when the delta has grown to dominate the reference
+// (or the reference orbit is exhausted), the delta is reset relative to a
+// fresh reference iteration to avoid glitches.
+pub(crate) fn calculate_perturbed(
+    delta_c_re: f64,
+    delta_c_im: f64,
+    orbit: &[(f64, f64)],
+    max_iter: u32,
+) -> f64 {
+    let mut dre = 0.0;
+    let mut dim = 0.0;
+    let mut ref_idx = 0usize;
+
+    // Step `n` produces `z_{n+1}`, so stopping one short of `max_iter` tests
+    // exactly the orbit points `calculate_fractal` tests (z_0 = 0 never escapes).
+    for n in 0..max_iter.saturating_sub(1) {
+        let (zre, zim) = orbit[ref_idx];
+
+        let new_dre = 2.0 * (zre * dre - zim * dim) + (dre * dre - dim * dim) + delta_c_re;
+        let new_dim = 2.0 * (zre * dim + zim * dre) + 2.0 * dre * dim + delta_c_im;
+        dre = new_dre;
+        dim = new_dim;
+        ref_idx += 1;
+
+        let (ref_re, ref_im) = orbit.get(ref_idx).copied().unwrap_or((zre, zim));
+        let actual_re = ref_re + dre;
+        let actual_im = ref_im + dim;
+        let actual_mag2 = actual_re * actual_re + actual_im * actual_im;
+
+        if actual_mag2 > BAILOUT * BAILOUT {
+            let modulus = actual_mag2.sqrt();
+            return (n + 1) as f64 + 1.0 - modulus.ln().log2();
+        }
+
+        let delta_mag2 = dre * dre + dim * dim;
+        if actual_mag2 < delta_mag2 || ref_idx >= orbit.len() - 1 {
+            dre = actual_re;
+            dim = actual_im;
+            ref_idx = 0;
+        }
+    }
+
+    max_iter as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Pins `calculate_perturbed` against direct (non-perturbed) iteration of
+    // the same absolute point, right at the scale where `PERTURBATION_RANGE_THRESHOLD`
+    // switches a render over to this code path. Direct f64 iteration is still
+    // reliable at this scale, so it's a trustworthy reference to pin against.
+    #[test]
+    fn perturbed_matches_direct_near_threshold() {
+        let max_iter = 200;
+        // A point just outside the Mandelbrot boundary, chosen so nearby
+        // offsets escape at varied iteration counts (roughly 120 to 156)
+        // rather than all landing in the interior.
+        let center_re = 0.377_202_013_508_618;
+        let center_im = 0.164_253_751_574_265;
+        let range = crate::PERTURBATION_RANGE_THRESHOLD * 10.0;
+
+        let orbit = reference_orbit(center_re, center_im, max_iter);
+
+        let offsets = [
+            (0.0, 0.0),
+            (range * 0.25, 0.0),
+            (0.0, range * 0.25),
+            (-range * 0.3, range * 0.2),
+        ];
+
+        for (delta_re, delta_im) in offsets {
+            let perturbed = calculate_perturbed(delta_re, delta_im, &orbit, max_iter);
+
+            let direct = crate::calculate_fractal(
+                0.0,
+                0.0,
+                center_re + delta_re,
+                center_im + delta_im,
+                max_iter,
+                crate::FractalKind::Mandelbrot,
+                2,
+            );
+
+            assert!(
+                (perturbed - direct).abs() < 0.5,
+                "perturbed {perturbed} vs direct {direct} diverged at offset ({delta_re}, {delta_im})"
+            );
+        }
+    }
+}
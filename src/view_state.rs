@@ -0,0 +1,32 @@
+// A compact, stable-schema snapshot of a view: everything needed to
+// reproduce an exact render. Exported/imported as JSON via `get_state()`/
+// `set_state()` so a view can be bookmarked or shared in a URL, and doubles
+// as a deterministic scene description for reproducing a specific render.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct ViewState {
+    pub(crate) center_x: f64,
+    pub(crate) center_y: f64,
+    pub(crate) range: f64,
+    pub(crate) max_iter: u32,
+    pub(crate) fractal: String,
+    pub(crate) power: u32,
+    pub(crate) julia_seed: Option<(f64, f64)>,
+    pub(crate) palette: String,
+    pub(crate) palette_cycle: f64,
+}
+
+impl ViewState {
+    // Clamps/normalizes fields loaded from an untrusted string so a bad or
+    // hand-edited URL can't put the viewport into a broken state (zero/negative
+    // range, a runaway max_iter, an out-of-range palette cycle, ...).
+    pub(crate) fn clamp(mut self) -> Self {
+        self.range = self.range.clamp(1e-300, 10.0);
+        self.max_iter = self.max_iter.clamp(1, 100_000);
+        self.power = self.power.clamp(2, crate::MAX_FRACTAL_POWER);
+        self.palette_cycle = self.palette_cycle.rem_euclid(1.0);
+        self
+    }
+}